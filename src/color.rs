@@ -0,0 +1,13 @@
+/// An 8-bit-per-channel RGB color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    pub fn new_color(red: u8, green: u8, blue: u8) -> Color {
+        Color { red, green, blue }
+    }
+}
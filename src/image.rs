@@ -0,0 +1,189 @@
+use crate::color::Color;
+use crate::matrix::Matrix;
+use std::fs::File;
+use std::io::Write;
+
+/// A `width`x`height` raster screen, stored as a flat row-major buffer of
+/// pixels with `(0, 0)` at the bottom-left corner.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Color>,
+    depth_buffer: Vec<f32>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Color::new_color(0, 0, 0); width * height],
+            depth_buffer: vec![f32::NEG_INFINITY; width * height],
+        }
+    }
+
+    /// Resets every pixel to black and the depth buffer to -infinity.
+    pub fn clear(&mut self) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = Color::new_color(0, 0, 0);
+        }
+        for depth in self.depth_buffer.iter_mut() {
+            *depth = f32::NEG_INFINITY;
+        }
+    }
+
+    fn plot(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let row = self.height - 1 - y as usize;
+        self.pixels[row * self.width + x as usize] = color;
+    }
+
+    /// Plots `(x, y, z)`, but only if `z` is closer to the camera than
+    /// whatever is already stored at that pixel in the depth buffer.
+    fn plot_with_depth(&mut self, x: i32, y: i32, z: f32, color: Color) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let row = self.height - 1 - y as usize;
+        let index = row * self.width + x as usize;
+        if z > self.depth_buffer[index] {
+            self.depth_buffer[index] = z;
+            self.pixels[index] = color;
+        }
+    }
+
+    /// Draws every line in `points` (consecutive pairs of rows) with
+    /// Bresenham's algorithm.
+    pub fn draw_lines(&mut self, points: &Matrix, color: Color) {
+        for pair in points.matrix_array.chunks(2) {
+            if pair.len() < 2 {
+                continue;
+            }
+            self.draw_line(
+                pair[0][0] as i32,
+                pair[0][1] as i32,
+                pair[1][0] as i32,
+                pair[1][1] as i32,
+                color,
+            );
+        }
+    }
+
+    fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        let (mut x, mut y) = (x0, y0);
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Flat-shades every triangle in `polygons` (consecutive triples of
+    /// rows) with `color` via scanline fill, depth-testing each pixel
+    /// against the depth buffer and culling backfacing triangles.
+    pub fn draw_polygons(&mut self, polygons: &Matrix, color: Color) {
+        for triangle in polygons.matrix_array.chunks(3) {
+            if triangle.len() < 3 {
+                continue;
+            }
+            let triangle = [triangle[0], triangle[1], triangle[2]];
+            if surface_normal_z(&triangle) <= 0.0 {
+                continue;
+            }
+            self.fill_triangle(triangle, color);
+        }
+    }
+
+    fn fill_triangle(&mut self, triangle: [[f32; 4]; 3], color: Color) {
+        let y_min = triangle
+            .iter()
+            .map(|p| p[1])
+            .fold(f32::INFINITY, f32::min)
+            .floor()
+            .max(0.0) as i32;
+        let y_max = triangle
+            .iter()
+            .map(|p| p[1])
+            .fold(f32::NEG_INFINITY, f32::max)
+            .ceil()
+            .min(self.height as f32 - 1.0) as i32;
+
+        for y in y_min..=y_max {
+            let mut intersections = vec![];
+            for edge in 0..3 {
+                let (x0, y0, z0) = (triangle[edge][0], triangle[edge][1], triangle[edge][2]);
+                let (x1, y1, z1) = (
+                    triangle[(edge + 1) % 3][0],
+                    triangle[(edge + 1) % 3][1],
+                    triangle[(edge + 1) % 3][2],
+                );
+                if (y0 <= y as f32 && y1 > y as f32) || (y1 <= y as f32 && y0 > y as f32) {
+                    let t = (y as f32 - y0) / (y1 - y0);
+                    intersections.push((x0 + t * (x1 - x0), z0 + t * (z1 - z0)));
+                }
+            }
+            intersections.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            if let [(x_start, z_start), .., (x_end, z_end)] = intersections[..] {
+                let x0 = x_start.round() as i32;
+                let x1 = x_end.round() as i32;
+                for x in x0..=x1 {
+                    let t = if x1 == x0 {
+                        0.0
+                    } else {
+                        (x - x0) as f32 / (x1 - x0) as f32
+                    };
+                    let z = z_start + t * (z_end - z_start);
+                    self.plot_with_depth(x, y, z, color);
+                }
+            }
+        }
+    }
+
+    /// Prints a one-line note with the screen's size to the terminal; the
+    /// pixels themselves are not shown.
+    pub fn display(&self) {
+        println!("displaying a {}x{} image", self.width, self.height);
+    }
+
+    /// Writes the screen out as a plain PPM. The caller is expected to then
+    /// shell out to `magick convert` to transcode it to `fname`'s extension.
+    pub fn create_file(&self, fname: &str) {
+        let mut file = File::create(fname).expect("failed to create image file");
+        writeln!(file, "P3").unwrap();
+        writeln!(file, "{} {}", self.width, self.height).unwrap();
+        writeln!(file, "255").unwrap();
+        for pixel in &self.pixels {
+            writeln!(file, "{} {} {}", pixel.red, pixel.green, pixel.blue).unwrap();
+        }
+    }
+}
+
+/// The z component of the triangle's surface normal, computed via the cross
+/// product of two of its edges. A triangle with a non-positive z component
+/// faces away from the camera and should be culled.
+fn surface_normal_z(triangle: &[[f32; 4]; 3]) -> f32 {
+    let a = triangle[0];
+    let b = triangle[1];
+    let c = triangle[2];
+    let (ax, ay) = (b[0] - a[0], b[1] - a[1]);
+    let (bx, by) = (c[0] - a[0], c[1] - a[1]);
+    ax * by - ay * bx
+}
@@ -5,10 +5,10 @@ use matrix::Matrix;
 use parser::parse_file;
 use std::env;
 mod color;
-mod draw;
 mod image;
 mod matrix;
 mod parser;
+mod transform;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -16,24 +16,9 @@ fn main() {
     let color = Color::new_color(0, 255, 0);
     let mut edges = Matrix::new(0, 0);
     let mut polygons = Matrix::new(0, 0);
-    let mut transform = Matrix::new(4, 4);
     if args.len() > 1 && args[1] == "art" {
-        parse_file(
-            "macprowheels",
-            &mut edges,
-            &mut polygons,
-            &mut transform,
-            &mut screen,
-            color,
-        );
+        parse_file("macprowheels", &mut edges, &mut polygons, &mut screen, color);
     } else {
-        parse_file(
-            "script",
-            &mut edges,
-            &mut polygons,
-            &mut transform,
-            &mut screen,
-            color,
-        );
+        parse_file("script", &mut edges, &mut polygons, &mut screen, color);
     }
 }
@@ -0,0 +1,362 @@
+use std::f32;
+
+/// The cubic curve bases `add_curve` knows how to evaluate.
+pub enum CurveType {
+    Hermite,
+    Bezier,
+    BSpline,
+}
+
+/// The default curve/circle tolerance, in pixels of chord error, used whenever a command omits its trailing tolerance
+/// argument.
+pub const DEFAULT_TOLERANCE: f32 = 0.1;
+
+/// A matrix of homogeneous `[x, y, z, w]` rows.
+///
+/// Used both as a fixed 4x4 transformation matrix (`Matrix::new(4, 4)`) and
+/// as a growable list of edge/polygon vertices (`Matrix::new(0, 0)`, grown
+/// with `add_edge`/`add_triangle` and friends). `multiply_matrixes` right-
+/// multiplies every row of `self` by `other`, which is what lets the same
+/// method both transform a point list by a transform matrix and accumulate
+/// a new operation onto a transform matrix.
+#[derive(Clone)]
+pub struct Matrix {
+    pub matrix_array: Vec<[f32; 4]>,
+}
+
+impl Matrix {
+    pub fn new(rows: usize, _cols: usize) -> Matrix {
+        Matrix {
+            matrix_array: vec![[0.0, 0.0, 0.0, 0.0]; rows],
+        }
+    }
+
+    /// Resets `self` to the 4x4 identity matrix.
+    pub fn identity(&mut self) {
+        self.matrix_array = vec![
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+    }
+
+    /// Right-multiplies every row of `self` by the 4x4 matrix `other`.
+    pub fn multiply_matrixes(&mut self, other: &Matrix) {
+        for row in self.matrix_array.iter_mut() {
+            let mut result = [0.0; 4];
+            for (j, slot) in result.iter_mut().enumerate() {
+                *slot = (0..4).map(|k| row[k] * other.matrix_array[k][j]).sum();
+            }
+            *row = result;
+        }
+    }
+
+    pub fn make_scale(sx: f32, sy: f32, sz: f32) -> Matrix {
+        Matrix {
+            matrix_array: vec![
+                [sx, 0.0, 0.0, 0.0],
+                [0.0, sy, 0.0, 0.0],
+                [0.0, 0.0, sz, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn make_translate(tx: f32, ty: f32, tz: f32) -> Matrix {
+        Matrix {
+            matrix_array: vec![
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [tx, ty, tz, 1.0],
+            ],
+        }
+    }
+
+    pub fn make_rot_x(theta: f32) -> Matrix {
+        let (s, c) = theta.to_radians().sin_cos();
+        Matrix {
+            matrix_array: vec![
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, c, s, 0.0],
+                [0.0, -s, c, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn make_rot_y(theta: f32) -> Matrix {
+        let (s, c) = theta.to_radians().sin_cos();
+        Matrix {
+            matrix_array: vec![
+                [c, 0.0, -s, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [s, 0.0, c, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn make_rot_z(theta: f32) -> Matrix {
+        let (s, c) = theta.to_radians().sin_cos();
+        Matrix {
+            matrix_array: vec![
+                [c, s, 0.0, 0.0],
+                [-s, c, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Adds the line `(x0, y0, z0)-(x1, y1, z1)` to the edge matrix.
+    pub fn add_edge(&mut self, x0: f32, y0: f32, z0: f32, x1: f32, y1: f32, z1: f32) {
+        self.matrix_array.push([x0, y0, z0, 1.0]);
+        self.matrix_array.push([x1, y1, z1, 1.0]);
+    }
+
+    /// Adds the triangle `a-b-c` to the polygon matrix.
+    fn add_triangle(&mut self, a: (f32, f32, f32), b: (f32, f32, f32), c: (f32, f32, f32)) {
+        self.matrix_array.push([a.0, a.1, a.2, 1.0]);
+        self.matrix_array.push([b.0, b.1, b.2, 1.0]);
+        self.matrix_array.push([c.0, c.1, c.2, 1.0]);
+    }
+
+    /// Adds a circle centered at `(cx, cy, cz)` with radius `r` to the edge
+    /// matrix, subdividing just enough that the chord error stays under
+    /// `tolerance` pixels.
+    pub fn add_circle(&mut self, cx: f32, cy: f32, cz: f32, r: f32, tolerance: f32) {
+        let steps = circle_steps_for_tolerance(r, tolerance);
+        let mut prev = (cx + r, cy);
+        for step in 1..=steps {
+            let t = 2.0 * f32::consts::PI * step as f32 / steps as f32;
+            let next = (cx + r * t.cos(), cy + r * t.sin());
+            self.add_edge(prev.0, prev.1, cz, next.0, next.1, cz);
+            prev = next;
+        }
+    }
+
+    /// Adds a Hermite, Bezier, or (single-segment) B-spline curve to the
+    /// edge matrix, recursively subdividing via de Casteljau until the
+    /// control polygon's deviation from its chord is under `tolerance`
+    /// pixels. For `Hermite`, `p0`/`p1` are the curve's endpoints and
+    /// `p2`/`p3` its two tangent vectors; for `Bezier`, `p0`/`p3` are the
+    /// endpoints and `p1`/`p2` the interior control points; for `BSpline`,
+    /// `p0`..`p3` are one window of control points, none of which the curve
+    /// passes through.
+    pub fn add_curve(
+        &mut self,
+        p0: (f32, f32),
+        p1: (f32, f32),
+        p2: (f32, f32),
+        p3: (f32, f32),
+        tolerance: f32,
+        curve_type: &CurveType,
+    ) {
+        let control = match curve_type {
+            CurveType::Hermite => hermite_to_bezier(p0, p1, p2, p3),
+            CurveType::Bezier => [p0, p1, p2, p3],
+            CurveType::BSpline => bspline_window_to_bezier([p0, p1, p2, p3]),
+        };
+
+        self.add_bezier_segment(control, tolerance);
+    }
+
+    /// Adds a chain of uniform cubic B-spline segments to the edge matrix.
+    /// Every consecutive window of 4 control points forms one segment, so
+    /// C2 continuity carries across the whole chain; `control_points` must
+    /// have at least 4 entries.
+    pub fn add_bspline(&mut self, control_points: &[(f32, f32)], tolerance: f32) {
+        for window in control_points.windows(4) {
+            self.add_curve(
+                window[0],
+                window[1],
+                window[2],
+                window[3],
+                tolerance,
+                &CurveType::BSpline,
+            );
+        }
+    }
+
+    /// Subdivides `control` (a cubic Bezier) down to `tolerance` and adds
+    /// the resulting polyline to the edge matrix.
+    fn add_bezier_segment(&mut self, control: [(f32, f32); 4], tolerance: f32) {
+        let mut curve_points = vec![control[0]];
+        subdivide_bezier(control, tolerance, 0, &mut curve_points);
+        for pair in curve_points.windows(2) {
+            self.add_edge(pair[0].0, pair[0].1, 0.0, pair[1].0, pair[1].1, 0.0);
+        }
+    }
+
+    /// Adds a rectangular prism to the polygon matrix.
+    pub fn add_box(&mut self, x: f32, y: f32, z: f32, width: f32, height: f32, depth: f32) {
+        let (x0, x1) = (x, x + width);
+        let (y0, y1) = (y, y - height);
+        let (z0, z1) = (z, z - depth);
+
+        let mut add_face = |s: &mut Matrix,
+                             a: (f32, f32, f32),
+                             b: (f32, f32, f32),
+                             c: (f32, f32, f32),
+                             d: (f32, f32, f32)| {
+            s.add_triangle(a, b, c);
+            s.add_triangle(a, c, d);
+        };
+
+        add_face(self, (x0, y0, z0), (x0, y1, z0), (x1, y1, z0), (x1, y0, z0));
+        add_face(self, (x1, y0, z1), (x1, y1, z1), (x0, y1, z1), (x0, y0, z1));
+        add_face(self, (x0, y0, z0), (x1, y0, z0), (x1, y0, z1), (x0, y0, z1));
+        add_face(self, (x0, y1, z1), (x1, y1, z1), (x1, y1, z0), (x0, y1, z0));
+        add_face(self, (x0, y0, z1), (x0, y1, z1), (x0, y1, z0), (x0, y0, z0));
+        add_face(self, (x1, y0, z0), (x1, y1, z0), (x1, y1, z1), (x1, y0, z1));
+    }
+
+    /// Adds a sphere centered at `(cx, cy, cz)` with radius `r` to the
+    /// polygon matrix, deriving the latitude/longitude step count from
+    /// `tolerance` the same way `add_circle` does for a single ring.
+    pub fn add_sphere(&mut self, cx: f32, cy: f32, cz: f32, r: f32, tolerance: f32) {
+        let lon_steps = circle_steps_for_tolerance(r, tolerance);
+        let lat_steps = (lon_steps / 2).max(2);
+        let ring = |i: usize, j: usize| -> (f32, f32, f32) {
+            let phi = f32::consts::PI * i as f32 / lat_steps as f32 - f32::consts::PI / 2.0;
+            let theta = 2.0 * f32::consts::PI * j as f32 / lon_steps as f32;
+            (
+                cx + r * phi.cos() * theta.cos(),
+                cy + r * phi.sin(),
+                cz + r * phi.cos() * theta.sin(),
+            )
+        };
+
+        for i in 0..lat_steps {
+            for j in 0..lon_steps {
+                let p0 = ring(i, j);
+                let p1 = ring(i, j + 1);
+                let p2 = ring(i + 1, j + 1);
+                let p3 = ring(i + 1, j);
+                self.add_triangle(p0, p1, p2);
+                self.add_triangle(p0, p2, p3);
+            }
+        }
+    }
+
+    /// Adds a torus centered at `(cx, cy, cz)` to the polygon matrix.
+    /// `r0` is the radius of the tube, `r1` is the distance from the
+    /// torus's center to the center of the tube.
+    pub fn add_torus(&mut self, cx: f32, cy: f32, cz: f32, r0: f32, r1: f32, tolerance: f32) {
+        let tube_steps = circle_steps_for_tolerance(r0, tolerance);
+        let ring_steps = circle_steps_for_tolerance(r1, tolerance);
+        let point = |i: usize, j: usize| -> (f32, f32, f32) {
+            let phi = 2.0 * f32::consts::PI * i as f32 / tube_steps as f32;
+            let theta = 2.0 * f32::consts::PI * j as f32 / ring_steps as f32;
+            let x = theta.cos() * (r0 * phi.cos() + r1);
+            let y = r0 * phi.sin();
+            let z = -theta.sin() * (r0 * phi.cos() + r1);
+            (cx + x, cy + y, cz + z)
+        };
+
+        for i in 0..tube_steps {
+            for j in 0..ring_steps {
+                let p0 = point(i, j);
+                let p1 = point(i + 1, j);
+                let p2 = point(i + 1, j + 1);
+                let p3 = point(i, j + 1);
+                self.add_triangle(p0, p1, p2);
+                self.add_triangle(p0, p2, p3);
+            }
+        }
+    }
+}
+
+/// Converts Hermite control data (two endpoints `p0`/`p1` and their tangent
+/// vectors `r0`/`r1`) into the equivalent cubic Bezier control points so both
+/// curve types can share the same de Casteljau subdivision.
+fn hermite_to_bezier(
+    p0: (f32, f32),
+    p1: (f32, f32),
+    r0: (f32, f32),
+    r1: (f32, f32),
+) -> [(f32, f32); 4] {
+    [
+        p0,
+        (p0.0 + r0.0 / 3.0, p0.1 + r0.1 / 3.0),
+        (p1.0 - r1.0 / 3.0, p1.1 - r1.1 / 3.0),
+        p1,
+    ]
+}
+
+/// Converts one window of 4 uniform cubic B-spline control points
+/// `[P0, P1, P2, P3]` into the equivalent cubic Bezier control points, per
+/// the standard B-spline-to-Bezier basis change.
+fn bspline_window_to_bezier(window: [(f32, f32); 4]) -> [(f32, f32); 4] {
+    let [p0, p1, p2, p3] = window;
+    let combine = |terms: &[((f32, f32), f32)]| -> (f32, f32) {
+        let x: f32 = terms.iter().map(|(p, w)| p.0 * w).sum();
+        let y: f32 = terms.iter().map(|(p, w)| p.1 * w).sum();
+        (x / 6.0, y / 6.0)
+    };
+    [
+        combine(&[(p0, 1.0), (p1, 4.0), (p2, 1.0)]),
+        combine(&[(p1, 4.0), (p2, 2.0)]),
+        combine(&[(p1, 2.0), (p2, 4.0)]),
+        combine(&[(p1, 1.0), (p2, 4.0), (p3, 1.0)]),
+    ]
+}
+
+/// The max perpendicular distance of the control polygon's interior points
+/// to the chord from the first to the last control point.
+fn flatness(control: &[(f32, f32); 4]) -> f32 {
+    let distance = |p: (f32, f32)| -> f32 {
+        let (a, b) = (control[0], control[3]);
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len < 1e-6 {
+            return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+        }
+        ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / len
+    };
+    distance(control[1]).max(distance(control[2]))
+}
+
+/// Splits a cubic Bezier's control points at `t = 0.5` via de Casteljau.
+fn split_bezier(control: &[(f32, f32); 4]) -> ([(f32, f32); 4], [(f32, f32); 4]) {
+    let mid = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+    let p01 = mid(control[0], control[1]);
+    let p12 = mid(control[1], control[2]);
+    let p23 = mid(control[2], control[3]);
+    let p012 = mid(p01, p12);
+    let p123 = mid(p12, p23);
+    let p0123 = mid(p012, p123);
+    (
+        [control[0], p01, p012, p0123],
+        [p0123, p123, p23, control[3]],
+    )
+}
+
+const MAX_SUBDIVISION_DEPTH: u32 = 20;
+
+/// Recursively subdivides `control` until its flatness is under `tolerance`,
+/// appending each resulting segment's endpoint to `curve_points`.
+fn subdivide_bezier(
+    control: [(f32, f32); 4],
+    tolerance: f32,
+    depth: u32,
+    curve_points: &mut Vec<(f32, f32)>,
+) {
+    if flatness(&control) <= tolerance || depth >= MAX_SUBDIVISION_DEPTH {
+        curve_points.push(control[3]);
+        return;
+    }
+    let (left, right) = split_bezier(&control);
+    subdivide_bezier(left, tolerance, depth + 1, curve_points);
+    subdivide_bezier(right, tolerance, depth + 1, curve_points);
+}
+
+/// Picks a step count so a regular polygon inscribed in a circle of the
+/// given `radius` has max chord error under `tolerance` pixels.
+fn circle_steps_for_tolerance(radius: f32, tolerance: f32) -> usize {
+    let radius = radius.abs().max(1e-3);
+    let tolerance = tolerance.max(1e-3);
+    ((f32::consts::PI / (tolerance / radius).sqrt()).ceil() as usize).max(3)
+}
@@ -2,7 +2,11 @@ use crate::color::Color;
 use crate::image::Image;
 use crate::matrix::CurveType;
 use crate::matrix::Matrix;
+use crate::matrix::DEFAULT_TOLERANCE;
+use crate::transform::Transform;
+use std::collections::HashMap;
 use std::f32;
+use std::fs;
 use std::fs::File;
 use std::io::{self, prelude::*, BufReader};
 use std::process::Command;
@@ -17,24 +21,27 @@ use std::process::Command;
 
 /// The commands are as follows:
 
-/// line: add a line to the edge matrix -
+/// line: generate a line, multiply it by the top of the coordinate system
+/// stack, and add it to the edge matrix -
 /// takes 6 arguemnts (x0, y0, z0, x1, y1, z1)
 
-/// ident: set the transform matrix to the identity matrix -
+/// ident: set the top of the coordinate system stack to the identity matrix -
 
 /// scale: create a scale matrix,
-/// then multiply the transform matrix by the scale matrix -
+/// then multiply the top of the coordinate system stack by the scale matrix -
 /// takes 3 arguments (sx, sy, sz)
 
 /// translate: create a translation matrix,
-/// then multiply the transform matrix by the translation matrix -
+/// then multiply the top of the coordinate system stack by the translation matrix -
 /// takes 3 arguments (tx, ty, tz)
 
 /// rotate: create a rotation matrix,
-/// then multiply the transform matrix by the rotation matrix -
+/// then multiply the top of the coordinate system stack by the rotation matrix -
 /// takes 2 arguments (axis, theta) axis should be x y or z
 
-/// apply: apply the current transformation matrix to the edge matrix
+/// push: duplicate the top of the coordinate system stack and push the copy
+
+/// pop: remove the top of the coordinate system stack
 
 /// display: clear the screen, then
 /// draw the lines of the edge matrix to the screen
@@ -45,47 +52,197 @@ use std::process::Command;
 /// save the screen to a file -
 /// takes 1 argument (file name)
 
+/// save_svg: serialize the edge and polygon matrices directly to an SVG
+/// document instead of rasterizing - takes 1 argument (file name)
+
 /// quit: end parsing
 ///
 /// circle: add a circle to the edge matrix -
-/// takes 4 arguments (cx, cy, cz, r)
+/// takes 4 arguments (cx, cy, cz, r) plus an optional 5th (tolerance),
+/// which controls how finely the circle is subdivided - defaults to
+/// `matrix::DEFAULT_TOLERANCE` pixels of chord error
 ///
 /// hermite: add a hermite curve to the edge matrix -
-///          takes 8 arguments (x0, y0, x1, y1, rx0, ry0, rx1, ry1)
+///          takes 8 arguments (x0, y0, x1, y1, rx0, ry0, rx1, ry1) plus an
+///          optional 9th (tolerance), same meaning as for `circle`
 ///
 /// bezier: add a bezier curve to the edge matrix -
-///         takes 8 arguments (x0, y0, x1, y1, x2, y2, x3, y3)
+///         takes 8 arguments (x0, y0, x1, y1, x2, y2, x3, y3) plus an
+///         optional 9th (tolerance), same meaning as for `circle`
+///
+/// bspline: add a uniform cubic B-spline to the edge matrix -
+///          takes 4 or more (x, y) control points; every consecutive window
+///          of 4 points forms one C2-continuous segment, so more than 4
+///          points produces a chain. An odd count of trailing numbers means
+///          the last one is the tolerance, same meaning as for `circle`
 ///
 /// clear: clears the edge matrix of all points
 ///
-/// box: adds a rectangular prism (box) to the edge matrix - takes 6 parameters (x, y, z, width, height, depth)
+/// box: generates a rectangular prism (box), multiplies it by the top of the
+/// coordinate system stack, and adds it to the polygon matrix - takes 6 parameters (x, y, z, width, height, depth)
 ///
-/// sphere: adds a sphere to the edge matrix - takes 4 parameters (x, y, z, radius)
+/// sphere: generates a sphere, multiplies it by the top of the coordinate
+/// system stack, and adds it to the polygon matrix - takes 4 parameters (x, y, z, radius)
 ///
-/// torus: adds a torus to the edge matrix - takes 5 parameters (x, y, z, radius1, radius2)
+/// torus: generates a torus, multiplies it by the top of the coordinate
+/// system stack, and adds it to the polygon matrix - takes 5 parameters (x, y, z, radius1, radius2)
 ///
 /// radius1 is the radius of the circle that makes up the torus
 ///
 /// radius2 is the full radius of the torus (the translation factor). You can think of this as the distance from the center of the torus to the center of any circular slice of the torus.
 ///
+/// frames: sets the total number of frames to render for an animation -
+/// takes 1 argument (num_frames)
+///
+/// basename: sets the filename prefix used when saving animation frames -
+/// takes 1 argument (name)
+///
+/// vary: linearly interpolates a knob's value between two frames -
+/// takes 5 arguments (knob, start_frame, end_frame, start_value, end_value)
+///
+/// scale/translate/rotate may take an extra trailing argument naming a knob;
+/// while animating, the command's parameters are multiplied by that knob's
+/// value for the current frame
+///
 /// See the file script for an example of the file format
 pub fn parse_file(
     fname: &str,
     points: &mut Matrix,
     polygons: &mut Matrix,
-    transform: &mut Matrix,
     screen: &mut Image,
     color: Color,
 ) -> io::Result<()> {
     let file = File::open(&fname)?;
     let reader = BufReader::new(file);
     let mut doc_lines = vec![String::new(); 0];
-    let mut i = 0;
 
     for line in reader.lines() {
         doc_lines.push(line?);
     }
 
+    let (num_frames, basename, knob_tables) = scan_animation(&doc_lines);
+
+    if let Some(num_frames) = num_frames {
+        fs::create_dir_all("anim")?;
+        for frame in 0..num_frames {
+            let mut points = Matrix::new(0, 0);
+            let mut polygons = Matrix::new(0, 0);
+            let context = FrameContext {
+                knobs: &knob_tables[frame],
+                basename: &basename,
+                frame_number: frame,
+            };
+            run_commands(&doc_lines, &mut points, &mut polygons, screen, color, Some(&context));
+        }
+        return Ok(());
+    }
+
+    run_commands(&doc_lines, points, polygons, screen, color, None);
+    Ok(())
+}
+
+/// Scans `doc_lines` for the `frames`, `basename`, and `vary` commands and
+/// builds a per-frame table of knob values. Returns `None` for the frame
+/// count when no `frames` command is present, in which case the caller
+/// should fall back to the single-pass, non-animated behavior.
+fn scan_animation(doc_lines: &[String]) -> (Option<usize>, String, Vec<HashMap<String, f32>>) {
+    let mut num_frames: Option<usize> = None;
+    let mut basename = String::from("frame");
+    let mut varies = vec![];
+    let mut i = 0;
+
+    while i < doc_lines.len() {
+        match &*doc_lines[i] {
+            "frames" => {
+                i += 1;
+                num_frames = Some(doc_lines[i].trim().parse().unwrap());
+            }
+            "basename" => {
+                i += 1;
+                basename = doc_lines[i].trim().to_string();
+            }
+            "vary" => {
+                i += 1;
+                let tokens: Vec<&str> = doc_lines[i].split(' ').collect();
+                varies.push((
+                    tokens[0].to_string(),
+                    tokens[1].parse::<f32>().unwrap(),
+                    tokens[2].parse::<f32>().unwrap(),
+                    tokens[3].parse::<f32>().unwrap(),
+                    tokens[4].parse::<f32>().unwrap(),
+                ));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let frame_count = num_frames.unwrap_or(1);
+    let mut knob_tables = vec![HashMap::new(); frame_count];
+    for (knob, start_frame, end_frame, start_value, end_value) in varies {
+        for (frame, table) in knob_tables.iter_mut().enumerate() {
+            let frame = frame as f32;
+            let value = if frame < start_frame {
+                start_value
+            } else if frame > end_frame {
+                end_value
+            } else {
+                start_value
+                    + (end_value - start_value) * (frame - start_frame) / (end_frame - start_frame)
+            };
+            table.insert(knob.clone(), value);
+        }
+    }
+
+    (num_frames, basename, knob_tables)
+}
+
+/// Parses a whitespace-separated parameter line into numeric values, plus an
+/// optional trailing knob name if the last token isn't a number.
+fn split_knob_params(tokens: &[&str]) -> (Vec<f32>, Option<String>) {
+    if let Some((last, rest)) = tokens.split_last() {
+        if let Ok(value) = last.parse::<f32>() {
+            let mut params: Vec<f32> = rest.iter().map(|t| t.parse().unwrap()).collect();
+            params.push(value);
+            return (params, None);
+        }
+        let params = rest.iter().map(|t| t.parse().unwrap()).collect();
+        return (params, Some(last.to_string()));
+    }
+    (vec![], None)
+}
+
+/// The per-frame state threaded through `run_commands` while animating: the
+/// current frame's knob values, and where `save`/`save_svg` should write.
+struct FrameContext<'a> {
+    knobs: &'a HashMap<String, f32>,
+    basename: &'a str,
+    frame_number: usize,
+}
+
+fn knob_value(frame: Option<&FrameContext>, name: &Option<String>) -> f32 {
+    match name {
+        Some(name) => frame.and_then(|ctx| ctx.knobs.get(name)).copied().unwrap_or(1.0),
+        None => 1.0,
+    }
+}
+
+/// Runs the full command list once against `points`/`polygons`, starting the
+/// coordinate system stack from a single identity matrix. When `frame` is
+/// `Some`, its knob values scale the `scale`/`translate`/`rotate` commands'
+/// trailing-knob arguments, and `save`/`save_svg` write to
+/// `anim/<basename><frame_number%04>.<ext>` instead of the literal filename.
+fn run_commands(
+    doc_lines: &[String],
+    points: &mut Matrix,
+    polygons: &mut Matrix,
+    screen: &mut Image,
+    color: Color,
+    frame: Option<&FrameContext>,
+) {
+    let mut i = 0;
+    let mut stack = vec![Transform::identity()];
+
     while i < doc_lines.len() {
         match &*doc_lines[i] {
             "line" => {
@@ -94,67 +251,66 @@ pub fn parse_file(
                 for input in doc_lines[i].split(' ') {
                     params.push(input.parse().unwrap());
                 }
-                points.add_edge(
+                let mut edge = Matrix::new(0, 0);
+                edge.add_edge(
                     params[0], params[1], params[2], params[3], params[4], params[5],
                 );
+                edge.multiply_matrixes(stack.last().unwrap().matrix());
+                points.matrix_array.append(&mut edge.matrix_array);
             }
             "ident" => {
-                transform.identity();
+                *stack.last_mut().unwrap() = Transform::identity();
+            }
+            "push" => {
+                let top = stack.last().unwrap().clone();
+                stack.push(top);
+            }
+            "pop" => {
+                // The stack always holds at least the identity matrix it was
+                // seeded with; an unbalanced pop in the script leaves that
+                // invariant intact instead of popping it away.
+                if stack.len() > 1 {
+                    stack.pop();
+                }
             }
             "scale" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let tokens: Vec<&str> = doc_lines[i].split(' ').collect();
+                let (params, knob) = split_knob_params(&tokens);
+                let mult = knob_value(frame, &knob);
 
-                transform.multiply_matrixes(&Matrix::make_scale(params[0], params[1], params[2]));
+                let top = stack.pop().unwrap();
+                stack.push(top.then_scale(params[0] * mult, params[1] * mult, params[2] * mult));
             }
             "translate" | "move" => {
                 i += 1;
-                let mut params = vec![0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let tokens: Vec<&str> = doc_lines[i].split(' ').collect();
+                let (params, knob) = split_knob_params(&tokens);
+                let mult = knob_value(frame, &knob);
 
-                transform
-                    .multiply_matrixes(&Matrix::make_translate(params[0], params[1], params[2]));
+                let top = stack.pop().unwrap();
+                stack.push(top.then_translate(params[0] * mult, params[1] * mult, params[2] * mult));
             }
             "rotate" => {
                 i += 1;
-                let mut params = vec![""; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input);
-                }
+                let tokens: Vec<&str> = doc_lines[i].split(' ').collect();
+                let axis = tokens[0];
+                let (params, knob) = split_knob_params(&tokens[1..]);
+                let mult = knob_value(frame, &knob);
 
-                match params[0] {
-                    "x" => {
-                        transform
-                            .multiply_matrixes(&Matrix::make_rot_x(params[1].parse().unwrap()));
-                    }
-                    "y" => {
-                        transform
-                            .multiply_matrixes(&Matrix::make_rot_y(params[1].parse().unwrap()));
-                    }
-                    "z" => {
-                        transform
-                            .multiply_matrixes(&Matrix::make_rot_z(params[1].parse().unwrap()));
-                    }
+                let top = stack.pop().unwrap();
+                let theta = params[0] * mult;
+                stack.push(match axis {
+                    "x" => top.then_rotate_x(theta),
+                    "y" => top.then_rotate_y(theta),
+                    "z" => top.then_rotate_z(theta),
                     _ => {
                         panic!(
                             "Invalid input {} at 0 for rotation: please use x, y, or z.",
-                            params[0]
+                            axis
                         );
                     }
-                }
-            }
-            "apply" => {
-                if points.matrix_array.len() > 0 {
-                    points.multiply_matrixes(&transform);
-                }
-                if polygons.matrix_array.len() > 0 {
-                    polygons.multiply_matrixes(&transform);
-                }
+                });
             }
             "display" => {
                 screen.clear();
@@ -175,14 +331,27 @@ pub fn parse_file(
                     screen.draw_polygons(&polygons, color);
                 }
                 i += 1;
-                screen.create_file(&*doc_lines[i]);
+                let fname = match frame {
+                    Some(ctx) => format!("anim/{}{:04}.png", ctx.basename, ctx.frame_number),
+                    None => doc_lines[i].clone(),
+                };
+                screen.create_file(&fname);
                 Command::new("magick")
                     .arg("convert")
-                    .arg(&*doc_lines[i])
-                    .arg(&*doc_lines[i])
+                    .arg(&fname)
+                    .arg(&fname)
                     .spawn()
                     .expect("failed to convert image to desired format");
             }
+            "save_svg" => {
+                i += 1;
+                let fname = match frame {
+                    Some(ctx) => format!("anim/{}{:04}.svg", ctx.basename, ctx.frame_number),
+                    None => doc_lines[i].clone(),
+                };
+                write_svg(&fname, points, polygons, screen, color)
+                    .expect("failed to write svg file");
+            }
             "quit" => {
                 break;
             }
@@ -193,7 +362,11 @@ pub fn parse_file(
                     params.push(input.parse().unwrap());
                 }
 
-                points.add_circle(params[0], params[1], params[2], params[3], 100);
+                let tolerance = params.get(4).copied().unwrap_or(DEFAULT_TOLERANCE);
+                let mut circle = Matrix::new(0, 0);
+                circle.add_circle(params[0], params[1], params[2], params[3], tolerance);
+                circle.multiply_matrixes(stack.last().unwrap().matrix());
+                points.matrix_array.append(&mut circle.matrix_array);
             }
             "hermite" => {
                 i += 1;
@@ -202,18 +375,18 @@ pub fn parse_file(
                     params.push(input.parse().unwrap());
                 }
 
-                points.add_curve(
-                    params[0],
-                    params[1],
-                    params[2],
-                    params[3],
-                    params[4],
-                    params[5],
-                    params[6],
-                    params[7],
-                    100,
+                let tolerance = params.get(8).copied().unwrap_or(DEFAULT_TOLERANCE);
+                let mut curve = Matrix::new(0, 0);
+                curve.add_curve(
+                    (params[0], params[1]),
+                    (params[2], params[3]),
+                    (params[4], params[5]),
+                    (params[6], params[7]),
+                    tolerance,
                     &CurveType::Hermite,
                 );
+                curve.multiply_matrixes(stack.last().unwrap().matrix());
+                points.matrix_array.append(&mut curve.matrix_array);
             }
             "bezier" => {
                 i += 1;
@@ -222,18 +395,40 @@ pub fn parse_file(
                     params.push(input.parse().unwrap());
                 }
 
-                points.add_curve(
-                    params[0],
-                    params[1],
-                    params[2],
-                    params[3],
-                    params[4],
-                    params[5],
-                    params[6],
-                    params[7],
-                    100,
+                let tolerance = params.get(8).copied().unwrap_or(DEFAULT_TOLERANCE);
+                let mut curve = Matrix::new(0, 0);
+                curve.add_curve(
+                    (params[0], params[1]),
+                    (params[2], params[3]),
+                    (params[4], params[5]),
+                    (params[6], params[7]),
+                    tolerance,
                     &CurveType::Bezier,
                 );
+                curve.multiply_matrixes(stack.last().unwrap().matrix());
+                points.matrix_array.append(&mut curve.matrix_array);
+            }
+            "bspline" => {
+                i += 1;
+                let mut numbers = vec![0.0; 0];
+                for input in doc_lines[i].split(' ') {
+                    numbers.push(input.parse().unwrap());
+                }
+
+                let (tolerance, control_numbers) = if numbers.len() % 2 == 1 {
+                    (*numbers.last().unwrap(), &numbers[..numbers.len() - 1])
+                } else {
+                    (DEFAULT_TOLERANCE, &numbers[..])
+                };
+                let control_points: Vec<(f32, f32)> = control_numbers
+                    .chunks(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .collect();
+
+                let mut curve = Matrix::new(0, 0);
+                curve.add_bspline(&control_points, tolerance);
+                curve.multiply_matrixes(stack.last().unwrap().matrix());
+                points.matrix_array.append(&mut curve.matrix_array);
             }
             _ if doc_lines[i].starts_with('#') => {}
             "clear" => {
@@ -247,9 +442,12 @@ pub fn parse_file(
                     params.push(input.parse().unwrap());
                 }
 
-                polygons.add_box(
+                let mut shape = Matrix::new(0, 0);
+                shape.add_box(
                     params[0], params[1], params[2], params[3], params[4], params[5],
                 );
+                shape.multiply_matrixes(stack.last().unwrap().matrix());
+                polygons.matrix_array.append(&mut shape.matrix_array);
             }
             "sphere" => {
                 i += 1;
@@ -258,7 +456,10 @@ pub fn parse_file(
                     params.push(input.parse().unwrap());
                 }
 
-                polygons.add_sphere(params[0], params[1], params[2], params[3], 20);
+                let mut shape = Matrix::new(0, 0);
+                shape.add_sphere(params[0], params[1], params[2], params[3], DEFAULT_TOLERANCE);
+                shape.multiply_matrixes(stack.last().unwrap().matrix());
+                polygons.matrix_array.append(&mut shape.matrix_array);
             }
             "torus" => {
                 i += 1;
@@ -267,7 +468,13 @@ pub fn parse_file(
                     params.push(input.parse().unwrap());
                 }
 
-                polygons.add_torus(params[0], params[1], params[2], params[3], params[4], 20);
+                let mut shape = Matrix::new(0, 0);
+                shape.add_torus(params[0], params[1], params[2], params[3], params[4], DEFAULT_TOLERANCE);
+                shape.multiply_matrixes(stack.last().unwrap().matrix());
+                polygons.matrix_array.append(&mut shape.matrix_array);
+            }
+            "frames" | "basename" | "vary" => {
+                i += 1;
             }
             _ => {
                 panic!("Invalid command {} at line {}.", doc_lines[i], i + 1);
@@ -275,5 +482,56 @@ pub fn parse_file(
         }
         i += 1;
     }
-    Ok(())
+}
+
+/// Serializes `points` (consecutive pairs of rows) as `<line>` elements and
+/// `polygons` (consecutive triples of rows) as `<polygon>` elements into an
+/// SVG document sized to `screen`, using `color` as the stroke/fill.
+/// Flips the y axis to match `Image`'s bottom-left-origin convention.
+fn write_svg(
+    fname: &str,
+    points: &Matrix,
+    polygons: &Matrix,
+    screen: &Image,
+    color: Color,
+) -> io::Result<()> {
+    let stroke = format!("rgb({},{},{})", color.red, color.green, color.blue);
+    let flip_y = |y: f32| screen.height as f32 - y;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">\n",
+        screen.width, screen.height
+    );
+
+    for pair in points.matrix_array.chunks(2) {
+        if pair.len() < 2 {
+            continue;
+        }
+        svg.push_str(&format!(
+            "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" />\n",
+            pair[0][0],
+            flip_y(pair[0][1]),
+            pair[1][0],
+            flip_y(pair[1][1]),
+            stroke
+        ));
+    }
+
+    for triangle in polygons.matrix_array.chunks(3) {
+        if triangle.len() < 3 {
+            continue;
+        }
+        let vertices: Vec<String> = triangle
+            .iter()
+            .map(|p| format!("{},{}", p[0], flip_y(p[1])))
+            .collect();
+        svg.push_str(&format!(
+            "  <polygon points=\"{}\" fill=\"{}\" stroke=\"{}\" />\n",
+            vertices.join(" "),
+            stroke,
+            stroke
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    fs::write(fname, svg)
 }
@@ -0,0 +1,129 @@
+use crate::matrix::Matrix;
+
+/// A chainable wrapper around a 4x4 transformation matrix.
+///
+/// `then_*` methods read top-to-bottom in application order:
+/// `Transform::identity().then_translate(x, y, z).then_rotate_z(theta)` first
+/// translates, then rotates. They're implemented as `post_*` (the new
+/// operation is multiplied onto the right of the accumulated matrix); the
+/// `pre_*` variants multiply onto the left instead, for callers that need to
+/// apply an operation *before* everything accumulated so far.
+///
+/// Deriving `Clone` here (and the `other.clone()` in `pre_multiply` below)
+/// relies on `Matrix` itself being `Clone`.
+#[derive(Clone)]
+pub struct Transform {
+    matrix: Matrix,
+}
+
+impl Transform {
+    /// Starts a chain from the identity matrix.
+    pub fn identity() -> Transform {
+        let mut matrix = Matrix::new(4, 4);
+        matrix.identity();
+        Transform { matrix }
+    }
+
+    /// Wraps an already-built matrix so it can keep being chained.
+    pub fn from_matrix(matrix: Matrix) -> Transform {
+        Transform { matrix }
+    }
+
+    /// The accumulated 4x4 matrix, for multiplying geometry against.
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    /// Applies `other` after everything accumulated so far.
+    pub fn post_multiply(mut self, other: &Matrix) -> Transform {
+        self.matrix.multiply_matrixes(other);
+        self
+    }
+
+    /// Applies `other` before everything accumulated so far.
+    pub fn pre_multiply(mut self, other: &Matrix) -> Transform {
+        let mut product = other.clone();
+        product.multiply_matrixes(&self.matrix);
+        self.matrix = product;
+        self
+    }
+
+    pub fn then_scale(self, sx: f32, sy: f32, sz: f32) -> Transform {
+        self.post_scale(sx, sy, sz)
+    }
+
+    pub fn post_scale(self, sx: f32, sy: f32, sz: f32) -> Transform {
+        let scale = Matrix::make_scale(sx, sy, sz);
+        self.post_multiply(&scale)
+    }
+
+    pub fn pre_scale(self, sx: f32, sy: f32, sz: f32) -> Transform {
+        let scale = Matrix::make_scale(sx, sy, sz);
+        self.pre_multiply(&scale)
+    }
+
+    pub fn then_translate(self, tx: f32, ty: f32, tz: f32) -> Transform {
+        self.post_translate(tx, ty, tz)
+    }
+
+    pub fn post_translate(self, tx: f32, ty: f32, tz: f32) -> Transform {
+        let translate = Matrix::make_translate(tx, ty, tz);
+        self.post_multiply(&translate)
+    }
+
+    pub fn pre_translate(self, tx: f32, ty: f32, tz: f32) -> Transform {
+        let translate = Matrix::make_translate(tx, ty, tz);
+        self.pre_multiply(&translate)
+    }
+
+    pub fn then_rotate_x(self, theta: f32) -> Transform {
+        self.post_rotate_x(theta)
+    }
+
+    pub fn post_rotate_x(self, theta: f32) -> Transform {
+        let rotate = Matrix::make_rot_x(theta);
+        self.post_multiply(&rotate)
+    }
+
+    pub fn pre_rotate_x(self, theta: f32) -> Transform {
+        let rotate = Matrix::make_rot_x(theta);
+        self.pre_multiply(&rotate)
+    }
+
+    pub fn then_rotate_y(self, theta: f32) -> Transform {
+        self.post_rotate_y(theta)
+    }
+
+    pub fn post_rotate_y(self, theta: f32) -> Transform {
+        let rotate = Matrix::make_rot_y(theta);
+        self.post_multiply(&rotate)
+    }
+
+    pub fn pre_rotate_y(self, theta: f32) -> Transform {
+        let rotate = Matrix::make_rot_y(theta);
+        self.pre_multiply(&rotate)
+    }
+
+    pub fn then_rotate_z(self, theta: f32) -> Transform {
+        self.post_rotate_z(theta)
+    }
+
+    pub fn post_rotate_z(self, theta: f32) -> Transform {
+        let rotate = Matrix::make_rot_z(theta);
+        self.post_multiply(&rotate)
+    }
+
+    pub fn pre_rotate_z(self, theta: f32) -> Transform {
+        let rotate = Matrix::make_rot_z(theta);
+        self.pre_multiply(&rotate)
+    }
+
+    /// Multiplies the point `(x, y, z)` through the accumulated matrix.
+    pub fn transform_point(&self, x: f32, y: f32, z: f32) -> (f32, f32, f32) {
+        let mut point = Matrix::new(0, 0);
+        point.matrix_array.push([x, y, z, 1.0]);
+        point.multiply_matrixes(&self.matrix);
+        let result = point.matrix_array[0];
+        (result[0], result[1], result[2])
+    }
+}